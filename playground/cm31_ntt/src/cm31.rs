@@ -3,15 +3,19 @@ use serde::{Deserialize, Serialize};
 use {
     crate::rm31::{P, RF},
     core::fmt::Display,
+    ff::Field,
     num_traits::{Zero, identities::One, pow::Pow},
     rand::{
         Rng,
         distr::{Distribution, StandardUniform},
     },
+    rand_core::RngCore,
     std::{
         convert::{From, Into},
+        iter::{Product, Sum},
         ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
     },
+    subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption},
 };
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
@@ -42,6 +46,15 @@ pub const W_4_NEG_1: CF = CF {
     b: RF { val: 0x7ffffffe },
 };
 
+/// A fixed quadratic non-residue in CF, used by [`Field::sqrt_ratio`]'s
+/// non-square branch: `1 + 2i` has norm `1^2 + 2^2 = 5`, and `5` is a
+/// quadratic non-residue mod `P` (an element of CF is a square iff its norm
+/// is a square in RF), so this is never itself a square in CF.
+const SQRT_RATIO_NON_RESIDUE: CF = CF {
+    a: RF { val: 1 },
+    b: RF { val: 2 },
+};
+
 /// Returns the 2nd to n-th roots of unity (inclusive).
 pub fn gen_roots_of_unity(n: usize) -> Vec<CF> {
     assert!(n > 1);
@@ -96,18 +109,14 @@ impl CF {
         CF { a: real, b: imag }
     }
 
-    pub fn try_inverse(&self) -> Option<Self> {
-        if self.a.val == 0 && self.b.val == 0 {
-            return None;
-        }
-
+    /// Constant-time inverse: `a^2 + b^2` is zero in RF iff `self` is zero
+    /// (since -1 is a non-residue in RF, CF is a genuine field extension), so
+    /// there's a single data-dependent bit here and it's carried entirely by
+    /// the validity `Choice` rather than an early return.
+    pub fn try_inverse(&self) -> CtOption<Self> {
         let a2b2 = (self.a * self.a + self.b * self.b).reduce();
-        if a2b2.is_zero() {
-            return None;
-        }
-
-        let a2b2_inv = a2b2.try_inverse().unwrap().reduce();
-        debug_assert!((a2b2 * a2b2_inv).reduce() == RF::new(1));
+        let a2b2_inv_opt = a2b2.try_inverse();
+        let a2b2_inv = a2b2_inv_opt.unwrap_or(RF::new(0)).reduce();
 
         let neg_b = self.b.neg();
         let a_neg_b = CF {
@@ -116,7 +125,7 @@ impl CF {
         };
 
         let result = a_neg_b.mul_by_f(a2b2_inv);
-        Some(result)
+        CtOption::new(result, Choice::from(a2b2_inv_opt.is_some() as u8))
     }
 
     pub fn reduce(self) -> CF {
@@ -180,52 +189,159 @@ impl CF {
         }
     }
 
-    /// Attempts to compute a square root of a complex element in CF.
-    pub fn try_sqrt(self) -> Option<CF> {
-        if self.is_zero() {
-            return Some(CF::zero());
-        }
+    /// Multiplies by the QM31 non-residue `R = 2 + i`. Avoids a full CF
+    /// multiply: `x * (2 + i) = x*2 + x*i`.
+    #[inline]
+    pub fn mul_by_r(self) -> Self {
+        (self + self) + self.mul_j()
+    }
 
+    /// Attempts to compute a square root of a complex element in CF, in
+    /// constant time: both candidate branches are evaluated unconditionally
+    /// and the result is built by `conditional_select`ing between them (and
+    /// the `self == 0` edge case) rather than by early-returning out of
+    /// whichever branch happens to work.
+    pub fn try_sqrt(self) -> CtOption<CF> {
         let two = RF::new(2);
         // 2 is invertible in RF; unwrap is safe since P ≠ 2.
         let two_inv = two.try_inverse().unwrap();
         let a = self.a;
         let b = self.b;
-        // Compute r = sqrt(a^2 + b^2) in RF.
+        // r = sqrt(a^2 + b^2) in RF.
         let norm = (a * a + b * b).reduce();
-        let r = norm.try_sqrt()?;
-
-        // Candidate branch 1: try x = sqrt((a + r)/2).
-        let candidate_x2 = ((a + r) * two_inv).reduce();
-        if let Some(x) = candidate_x2.try_sqrt() {
-            // If x ≠ 0 then we can recover y as b/(2x).
-            if !x.is_zero() {
-                let x_inv = x.try_inverse().unwrap();
-                let y = (b * two_inv * x_inv).reduce();
-                let candidate = CF { a: x, b: y }.reduce();
-                if candidate * candidate == self {
-                    return Some(candidate);
-                }
+        let r = norm.try_sqrt().unwrap_or(RF::new(0));
+
+        // Candidate branch 1: x1 = sqrt((a + r)/2), y1 = b * (2*x1)^-1.
+        let candidate_x1_sq = ((a + r) * two_inv).reduce();
+        let x1 = candidate_x1_sq.try_sqrt().unwrap_or(RF::new(0));
+        let x1_inv = (x1 + x1).try_inverse().unwrap_or(RF::new(0));
+        let y1 = (b * x1_inv).reduce();
+        let candidate1 = CF { a: x1, b: y1 }.reduce();
+        let valid1 = (candidate1 * candidate1).ct_eq(&self);
+
+        // Candidate branch 2: y2 = sqrt((r - a)/2), x2 = b * (2*y2)^-1.
+        let candidate_y2_sq = ((r - a) * two_inv).reduce();
+        let y2 = candidate_y2_sq.try_sqrt().unwrap_or(RF::new(0));
+        let y2_inv = (y2 + y2).try_inverse().unwrap_or(RF::new(0));
+        let x2 = (b * y2_inv).reduce();
+        let candidate2 = CF { a: x2, b: y2 }.reduce();
+        let valid2 = (candidate2 * candidate2).ct_eq(&self);
+
+        let chosen = CF::conditional_select(&candidate2, &candidate1, valid1);
+        let is_zero = self.ct_eq(&CF::zero());
+        let result = CF::conditional_select(&chosen, &CF::zero(), is_zero);
+
+        CtOption::new(result, valid1 | valid2 | is_zero)
+    }
+
+    /// Whether `self` has a square root in CF, without computing it.
+    pub fn is_square(&self) -> bool {
+        if self.is_zero() {
+            return true;
+        }
+        let two_inv = RF::new(2).try_inverse().unwrap();
+        let norm = (self.a * self.a + self.b * self.b).reduce();
+        let Some(r) = norm.try_sqrt() else {
+            return false;
+        };
+        let candidate_x2 = ((self.a + r) * two_inv).reduce();
+        if candidate_x2.try_sqrt().is_some() {
+            return true;
+        }
+        // With the other root `-r`: for b != 0, exactly one of (a + r)/2 and
+        // (a - r)/2 is a square whenever the norm is.
+        let candidate_x2_neg_r = ((self.a - r) * two_inv).reduce();
+        candidate_x2_neg_r.try_sqrt().is_some()
+    }
+
+    /// The Legendre symbol of `self`: `1` for a nonzero square, `-1` for a
+    /// non-square, `0` for zero.
+    pub fn legendre(&self) -> i32 {
+        if self.is_zero() {
+            0
+        } else if self.is_square() {
+            1
+        } else {
+            -1
+        }
+    }
+
+    /// Inverts every element of `elements` in place with a single field
+    /// inversion plus `3*(n-1)` multiplications (Montgomery's trick), which
+    /// matters when many elements need inverting at once (e.g. NTT/coset
+    /// division). Zero elements are skipped when accumulating the running
+    /// product and left untouched, so a single zero doesn't poison the batch.
+    pub fn batch_inverse(elements: &mut [CF]) {
+        let mut prefix = vec![CF::one(); elements.len()];
+        let mut acc = CF::one();
+        for (element, prefix) in elements.iter().zip(prefix.iter_mut()) {
+            *prefix = acc;
+            if !element.is_zero() {
+                acc *= *element;
             }
         }
 
-        // Candidate branch 2: try y = sqrt((r - a)/2).
-        let candidate_y2 = ((r - a) * two_inv).reduce();
-        if let Some(y) = candidate_y2.try_sqrt() {
-            if !y.is_zero() {
-                let y_inv = y.try_inverse().unwrap();
-                let x = (b * two_inv * y_inv).reduce();
-                let candidate = CF { a: x, b: y }.reduce();
-                if candidate * candidate == self {
-                    return Some(candidate);
-                }
+        let mut acc_inv = Option::<CF>::from(acc.try_inverse()).unwrap_or_else(CF::zero);
+
+        for (element, prefix) in elements.iter_mut().zip(prefix.iter()).rev() {
+            if element.is_zero() {
+                continue;
             }
+            let original = *element;
+            *element = *prefix * acc_inv;
+            acc_inv *= original;
         }
+    }
+
+    /// Non-mutating form of [`CF::batch_inverse`].
+    pub fn batch_inverse_to(elements: &[CF]) -> Vec<CF> {
+        let mut out = elements.to_vec();
+        CF::batch_inverse(&mut out);
+        out
+    }
+
+    /// Maps 16 uniformly-random bytes to a CF element with negligible bias,
+    /// for Fiat-Shamir transcripts that hash into bytes rather than directly
+    /// sampling via `StandardUniform`. Each 8-byte half becomes one RF
+    /// coordinate via the cheap Mersenne fold (`p = 2^31 - 1`).
+    pub fn from_uniform_bytes(bytes: &[u8; 16]) -> CF {
+        let real = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        let imag = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+        CF {
+            a: RF::new(mersenne_fold(real)),
+            b: RF::new(mersenne_fold(imag)),
+        }
+    }
+
+    /// Hashes an arbitrary byte slice down to a CF element via
+    /// [`CF::from_uniform_bytes`].
+    ///
+    /// Uses SHA-256 rather than `std`'s `DefaultHasher`: this is meant for
+    /// Fiat-Shamir transcript binding, which needs a hash that's actually
+    /// collision-resistant and stable across toolchains, neither of which
+    /// `DefaultHasher` (SipHash-1-3, an unspecified implementation detail)
+    /// provides.
+    pub fn hash_to_cf(bytes: &[u8]) -> CF {
+        use sha2::{Digest, Sha256};
 
-        None
+        let digest = Sha256::digest(bytes);
+        let mut wide = [0u8; 16];
+        wide.copy_from_slice(&digest[0..16]);
+        CF::from_uniform_bytes(&wide)
     }
 }
 
+/// Folds a 64-bit value into `[0, P)` using Mersenne's trick: since
+/// `2^31 ≡ 1 (mod P)`, splitting off 31-bit chunks and adding them back in
+/// is equivalent to a full modular reduction.
+fn mersenne_fold(mut x: u64) -> u32 {
+    let p = P as u64;
+    while x > p {
+        x = (x & p) + (x >> 31);
+    }
+    if x == p { 0 } else { x as u32 }
+}
+
 impl Zero for CF {
     #[inline]
     fn zero() -> CF {
@@ -396,6 +512,132 @@ impl Distribution<CF> for StandardUniform {
     }
 }
 
+impl Default for CF {
+    #[inline]
+    fn default() -> Self {
+        CF::zero()
+    }
+}
+
+impl ConstantTimeEq for CF {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.a.val.ct_eq(&other.a.val) & self.b.val.ct_eq(&other.b.val)
+    }
+}
+
+impl ConditionallySelectable for CF {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        CF {
+            a: RF {
+                val: u32::conditional_select(&a.a.val, &b.a.val, choice),
+            },
+            b: RF {
+                val: u32::conditional_select(&a.b.val, &b.b.val, choice),
+            },
+        }
+    }
+}
+
+impl Sum for CF {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(CF::zero(), Add::add)
+    }
+}
+
+impl<'a> Sum<&'a CF> for CF {
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.fold(CF::zero(), |acc, x| acc + *x)
+    }
+}
+
+impl Product for CF {
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(CF::one(), Mul::mul)
+    }
+}
+
+impl<'a> Product<&'a CF> for CF {
+    fn product<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.fold(CF::one(), |acc, x| acc * *x)
+    }
+}
+
+/// Drops `CF` into the broader `ff`/bellman/halo2-style ecosystem that's
+/// generic over `Field`. `invert`/`sqrt` delegate directly to the
+/// constant-time `try_inverse`/`try_sqrt`.
+impl Field for CF {
+    const ONE: Self = CF::new(1, 0);
+    const ZERO: Self = CF::new(0, 0);
+
+    fn random(mut rng: impl RngCore) -> Self {
+        CF {
+            a: RF::new(rng.next_u32()),
+            b: RF::new(rng.next_u32()),
+        }
+    }
+
+    fn is_zero(&self) -> Choice {
+        Choice::from(Zero::is_zero(self) as u8)
+    }
+
+    fn square(&self) -> Self {
+        // Karatsuba specialized for squaring: (a,b)^2 = (a^2 - b^2, 2ab).
+        let a2 = self.a * self.a;
+        let b2 = self.b * self.b;
+        let ab = self.a * self.b;
+        CF {
+            a: a2 - b2,
+            b: ab + ab,
+        }
+    }
+
+    fn double(&self) -> Self {
+        *self + *self
+    }
+
+    fn invert(&self) -> CtOption<Self> {
+        self.try_inverse()
+    }
+
+    fn sqrt_ratio(num: &Self, div: &Self) -> (Choice, Self) {
+        let div_inv = div.invert();
+        let ratio = div_inv.map(|div_inv| *num * div_inv).unwrap_or_else(CF::zero);
+
+        let candidate = ratio.try_sqrt();
+        let is_square = candidate.is_some() & div_inv.is_some();
+
+        // Per the `Field::sqrt_ratio` contract, the non-square branch must
+        // return `sqrt(non_residue * num/div)`, not an arbitrary value: since
+        // `ratio` isn't a square, `ratio * SQRT_RATIO_NON_RESIDUE` always is,
+        // so this candidate is well-defined whenever `candidate` above isn't.
+        let non_residue_candidate = (ratio * SQRT_RATIO_NON_RESIDUE).try_sqrt();
+
+        let result = CF::conditional_select(
+            &non_residue_candidate.unwrap_or_else(CF::zero),
+            &candidate.unwrap_or_else(CF::zero),
+            is_square,
+        );
+        (is_square, result)
+    }
+
+    fn sqrt(&self) -> CtOption<Self> {
+        (*self).try_sqrt()
+    }
+
+    fn pow_vartime<S: AsRef<[u64]>>(&self, exp: S) -> Self {
+        let mut result = CF::one();
+        for &limb in exp.as_ref().iter().rev() {
+            for i in (0..64).rev() {
+                result = result.square();
+                if (limb >> i) & 1 == 1 {
+                    result *= *self;
+                }
+            }
+        }
+        result
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use {
@@ -461,6 +703,48 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_batch_inverse() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut elements: Vec<CF> = (0..64).map(|_| rng.r#gen()).collect();
+        elements[10] = CF::zero();
+
+        let expected: Vec<CF> = elements
+            .iter()
+            .map(|x| {
+                if x.is_zero() {
+                    CF::zero()
+                } else {
+                    Option::from(x.try_inverse()).unwrap()
+                }
+            })
+            .collect();
+
+        let mut inverted = elements.clone();
+        CF::batch_inverse(&mut inverted);
+        assert_eq!(inverted, expected);
+
+        assert_eq!(CF::batch_inverse_to(&elements), expected);
+    }
+
+    #[test]
+    fn test_from_uniform_bytes_in_range() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..256 {
+            let mut bytes = [0u8; 16];
+            rng.fill(&mut bytes);
+            let x = CF::from_uniform_bytes(&bytes);
+            assert!(x.a.val < P);
+            assert!(x.b.val < P);
+        }
+    }
+
+    #[test]
+    fn test_hash_to_cf_deterministic() {
+        assert_eq!(CF::hash_to_cf(b"provekit"), CF::hash_to_cf(b"provekit"));
+        assert_ne!(CF::hash_to_cf(b"provekit"), CF::hash_to_cf(b"prove-kit"));
+    }
+
     #[test]
     fn test_pow() {
         let mut rng = ChaCha8Rng::seed_from_u64(0);
@@ -557,6 +841,16 @@ mod tests {
         assert_eq!(v * j, v_j);
     }
 
+    #[test]
+    fn test_mul_by_r() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let r = CF::new(2, 1);
+        for _ in 0..1023 {
+            let x: CF = rng.r#gen();
+            assert_eq!(x.mul_by_r(), x * r);
+        }
+    }
+
     #[test]
     fn test_mul_by_w8() {
         let mut rng = ChaCha8Rng::seed_from_u64(0);
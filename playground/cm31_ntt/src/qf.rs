@@ -0,0 +1,216 @@
+/// QM31 ("secure field") arithmetic: the degree-4 extension of M31 used for
+/// Fiat-Shamir soundness, built as a quadratic extension of `CF`.
+use {
+    crate::cm31::CF,
+    core::fmt::Display,
+    num_traits::{identities::One, pow::Pow, Zero},
+    rand::{
+        distr::{Distribution, StandardUniform},
+        Rng,
+    },
+    serde::{Deserialize, Serialize},
+    std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+/// `QF = CF[u] / (u^2 - R)`, with the non-residue `R = 2 + i` (`CF::new(2,
+/// 1)`), holding two `CF` coordinates `{c0, c1}` such that `self = c0 +
+/// c1 * u`.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct QF {
+    pub c0: CF,
+    pub c1: CF,
+}
+
+impl QF {
+    pub fn new(c0: CF, c1: CF) -> QF {
+        QF { c0, c1 }
+    }
+
+    /// Scales by a base-field (`CF`) element.
+    pub fn mul_by_cf(self, f: CF) -> QF {
+        QF {
+            c0: self.c0 * f,
+            c1: self.c1 * f,
+        }
+    }
+
+    pub fn try_inverse(&self) -> Option<QF> {
+        // n = c0^2 - R*c1^2; inverse = (c0 - c1*u) * n^-1.
+        let n = self.c0 * self.c0 - (self.c1 * self.c1).mul_by_r();
+        let n_inv: CF = Option::from(n.try_inverse())?;
+        Some(QF {
+            c0: self.c0 * n_inv,
+            c1: self.c1.neg() * n_inv,
+        })
+    }
+}
+
+impl Zero for QF {
+    #[inline]
+    fn zero() -> QF {
+        QF::new(CF::zero(), CF::zero())
+    }
+
+    #[inline]
+    fn is_zero(&self) -> bool {
+        self.c0.is_zero() && self.c1.is_zero()
+    }
+}
+
+impl One for QF {
+    #[inline]
+    fn one() -> QF {
+        QF::new(CF::one(), CF::zero())
+    }
+}
+
+impl Add for QF {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        QF::new(self.c0 + rhs.c0, self.c1 + rhs.c1)
+    }
+}
+
+impl AddAssign for QF {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for QF {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        QF::new(self.c0 - rhs.c0, self.c1 - rhs.c1)
+    }
+}
+
+impl SubAssign for QF {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul for QF {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        // (c0 + c1*u)(d0 + d1*u) = (c0*d0 + c1*d1*R, c0*d1 + c1*d0)
+        let c0 = self.c0 * rhs.c0 + (self.c1 * rhs.c1).mul_by_r();
+        let c1 = self.c0 * rhs.c1 + self.c1 * rhs.c0;
+        QF::new(c0, c1)
+    }
+}
+
+impl MulAssign for QF {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl Neg for QF {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        QF::new(-self.c0, -self.c1)
+    }
+}
+
+impl PartialEq for QF {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.c0 == other.c0 && self.c1 == other.c1
+    }
+}
+
+impl Eq for QF {}
+
+impl Display for QF {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} + {}u", self.c0, self.c1)
+    }
+}
+
+impl Pow<usize> for QF {
+    type Output = QF;
+
+    #[inline]
+    fn pow(self, exp: usize) -> Self::Output {
+        let mut result = QF::one();
+        let mut base = self;
+        let mut exp = exp;
+        while exp > 0 {
+            if exp % 2 == 1 {
+                result *= base;
+            }
+            base *= base;
+            exp /= 2;
+        }
+        result
+    }
+}
+
+impl Distribution<QF> for StandardUniform {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> QF {
+        QF {
+            c0: rng.r#gen(),
+            c1: rng.r#gen(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        num_traits::One,
+        rand_chacha::{rand_core::SeedableRng, ChaCha8Rng},
+    };
+
+    #[test]
+    fn test_one() {
+        assert_eq!(QF::one().c0, CF::new(1, 0));
+        assert_eq!(QF::one().c1, CF::new(0, 0));
+    }
+
+    #[test]
+    fn test_add_sub() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..128 {
+            let x: QF = rng.r#gen();
+            let y: QF = rng.r#gen();
+            assert_eq!((x + y) - y, x);
+        }
+    }
+
+    #[test]
+    fn test_inverse() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..128 {
+            let x: QF = rng.r#gen();
+            if x.is_zero() {
+                continue;
+            }
+            let x_inv = x.try_inverse().unwrap();
+            assert_eq!(x * x_inv, QF::one());
+        }
+    }
+
+    #[test]
+    fn test_mul_by_cf() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..128 {
+            let x: QF = rng.r#gen();
+            let f: CF = rng.r#gen();
+            assert_eq!(x.mul_by_cf(f), x * QF::new(f, CF::zero()));
+        }
+    }
+}
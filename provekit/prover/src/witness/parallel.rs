@@ -0,0 +1,467 @@
+//! Dependency-layered, parallel witness solving.
+//!
+//! Most `WitnessBuilder` variants only read witness indices that earlier
+//! builders have already filled in and write one (or a small, disjoint) set
+//! of indices of their own, which makes a whole layer of mutually-independent
+//! builders safe to solve concurrently. The one exception is
+//! `WitnessBuilder::Challenge`, which draws from the Fiat-Shamir transcript
+//! and must therefore run strictly in program order relative to every other
+//! challenge: challenges split the program into epochs, and only the
+//! builders *within* an epoch are reordered/parallelized.
+//!
+//! Concurrent access to the shared witness buffer goes through
+//! [`SharedWitness`], a thin `*mut Option<F>` + `len` pair with a
+//! hand-justified `unsafe impl Sync`. Unlike a `&mut`-aliasing scheme, no
+//! thread ever holds a `&mut` (or even a `&`) over more than the one slot it
+//! is touching: every access is a raw-pointer read or `ptr::write` of that
+//! single slot, so there's nothing for the compiler's aliasing model to
+//! object to. Soundness instead rests entirely on `layer_by_dependency`'s
+//! guarantee that within one layer, the set of indices any builder writes is
+//! disjoint from every index any *other* builder in that layer reads or
+//! writes — see `SharedWitness::{get, set}` for the exact invariant each
+//! needs. (`Cell`-based sharing was tried first and abandoned: `Cell<T>` is
+//! never `Sync`, so `&[Cell<Option<F>>]` can't cross the `rayon::Scope`
+//! boundary at all.)
+//!
+//! `DigitalDecomposition`/`SpiceWitnesses` builders call out to
+//! `dd_struct.solve_cells`/`spice_witnesses.{reads,writes,solve_cells}`,
+//! which this commit assumes exist on the solvers defined in `digits.rs`/
+//! `ram.rs`; those modules aren't part of this checkout, so that assumption
+//! can't be verified here and needs to land alongside (or be confirmed
+//! against) the real modules.
+
+use {
+    super::witness_builder::{AcirField, WitnessBuilderSolver},
+    acir::native_types::WitnessMap,
+    ark_std::{One, Zero},
+    provekit_common::{
+        utils::noir_to_native,
+        witness::{
+            ConstantOrR1CSWitness, ConstantTerm, ProductLinearTerm, SumTerm, WitnessBuilder,
+            WitnessCoefficient, BINOP_ATOMIC_BITS,
+        },
+        NoirElement,
+    },
+    rayon::prelude::*,
+    spongefish::ProverState,
+    std::collections::{HashMap, HashSet},
+};
+
+#[cfg(test)]
+use provekit_common::FieldElement;
+
+/// Solves a full program of `WitnessBuilder`s, parallelizing within each
+/// dependency layer of each challenge-delimited epoch.
+pub fn solve_parallel<F: AcirField>(
+    builders: &[WitnessBuilder<F>],
+    acir_witness_idx_to_value_map: &WitnessMap<NoirElement>,
+    witness: &mut [Option<F>],
+    transcript: &mut ProverState<F::Sponge, F>,
+) {
+    for epoch in builders.split_inclusive(|b| matches!(b, WitnessBuilder::Challenge(_))) {
+        let (parallel_part, challenge) = match epoch.split_last() {
+            Some((last, rest)) if matches!(last, WitnessBuilder::Challenge(_)) => {
+                (rest, Some(last))
+            }
+            _ => (epoch, None),
+        };
+
+        let shared = SharedWitness::new(witness);
+        for layer in layer_by_dependency(parallel_part) {
+            layer
+                .par_iter()
+                .for_each(|builder| solve_into_shared(builder, acir_witness_idx_to_value_map, &shared));
+        }
+
+        if let Some(challenge) = challenge {
+            // Challenge is never part of `parallel_part`, so it alone gets
+            // ordinary, exclusive `&mut` access to `witness`/`transcript`.
+            challenge.solve(acir_witness_idx_to_value_map, witness, transcript);
+        }
+    }
+}
+
+/// A raw `*mut Option<F>` + `len` over the witness buffer, shared read/write
+/// across threads without ever materializing an aliasing `&mut`.
+///
+/// `unsafe impl Sync` is justified only because every caller in this module
+/// accesses it exclusively through [`SharedWitness::get`]/[`SharedWitness::
+/// set`] under the discipline `layer_by_dependency` enforces: within a
+/// single layer, no two builders' write sets overlap, and no builder reads
+/// an index that any builder *in the same layer* writes (every read either
+/// has no writer in this epoch yet, or was written in a strictly earlier
+/// layer). That makes every `get`/`set` pair below touch disjoint slots, so
+/// there's no data race even though many threads hold this value at once.
+struct SharedWitness<F> {
+    ptr: *mut Option<F>,
+    len: usize,
+}
+
+unsafe impl<F: Send> Sync for SharedWitness<F> {}
+
+impl<F> SharedWitness<F> {
+    fn new(witness: &mut [Option<F>]) -> Self {
+        Self {
+            ptr: witness.as_mut_ptr(),
+            len: witness.len(),
+        }
+    }
+
+    /// Reads the value at `idx`.
+    ///
+    /// # Panics
+    /// Panics if `idx` is out of bounds or hasn't been written yet.
+    ///
+    /// # Safety invariant
+    /// The caller must ensure no other thread is concurrently *writing*
+    /// `idx` — guaranteed here because `idx` was either written in a
+    /// strictly earlier layer, or has no writer in this epoch at all.
+    fn get(&self, idx: usize) -> F
+    where
+        F: Copy,
+    {
+        assert!(idx < self.len, "witness index {idx} out of bounds");
+        // SAFETY: `idx < self.len`, and per the invariant above no thread is
+        // concurrently writing this slot, so this is a plain, non-racing
+        // read of a live `Option<F>`.
+        unsafe { (*self.ptr.add(idx)).expect("witness read before write") }
+    }
+
+    /// Writes `value` at `idx`.
+    ///
+    /// # Safety invariant
+    /// The caller must ensure no other thread is concurrently reading or
+    /// writing `idx` — guaranteed here because `layer_by_dependency` puts at
+    /// most one writer of any given index in each layer, and that index is
+    /// never read by another builder in the same layer.
+    fn set(&self, idx: usize, value: F) {
+        assert!(idx < self.len, "witness index {idx} out of bounds");
+        // SAFETY: `idx < self.len`, and per the invariant above this is the
+        // only concurrent access to this slot this layer, so writing
+        // through the raw pointer (never forming a `&mut` to the rest of
+        // the buffer) can't race or alias another thread's access.
+        unsafe { std::ptr::write(self.ptr.add(idx), Some(value)) };
+    }
+}
+
+/// Solves a single non-`Challenge` builder against a [`SharedWitness`].
+/// Mirrors `WitnessBuilderSolver::solve`'s arms exactly, modulo `get`/`set`
+/// in place of slice indexing; kept as a separate function (rather than
+/// reusing the `&mut`-based trait) so no thread ever needs a unique
+/// reference to shared memory.
+fn solve_into_shared<F: AcirField>(
+    builder: &WitnessBuilder<F>,
+    acir_witness_idx_to_value_map: &WitnessMap<NoirElement>,
+    witness: &SharedWitness<F>,
+) {
+    let get = |idx: usize| witness.get(idx);
+    let set = |idx: usize, value: F| witness.set(idx, value);
+
+    match builder {
+        WitnessBuilder::Constant(ConstantTerm(witness_idx, c)) => {
+            set(*witness_idx, *c);
+        }
+        WitnessBuilder::Acir(witness_idx, acir_witness_idx) => {
+            set(
+                *witness_idx,
+                noir_to_native(
+                    *acir_witness_idx_to_value_map
+                        .get_index(*acir_witness_idx as u32)
+                        .unwrap(),
+                ),
+            );
+        }
+        WitnessBuilder::Sum(witness_idx, operands) => {
+            let sum = operands
+                .iter()
+                .map(|SumTerm(coeff, idx)| match coeff {
+                    Some(coeff) => *coeff * get(*idx),
+                    None => get(*idx),
+                })
+                .fold(F::zero(), |acc, x| acc + x);
+            set(*witness_idx, sum);
+        }
+        WitnessBuilder::Product(witness_idx, a, b) => {
+            set(*witness_idx, get(*a) * get(*b));
+        }
+        WitnessBuilder::Inverse(witness_idx, operand_idx) => {
+            set(*witness_idx, get(*operand_idx).inverse().unwrap());
+        }
+        WitnessBuilder::CheckedInverse(witness_idx, operand_idx, is_zero_idx) => {
+            let operand = get(*operand_idx);
+            if operand.is_zero() {
+                set(*witness_idx, F::zero());
+                set(*is_zero_idx, F::one());
+            } else {
+                set(*witness_idx, operand.inverse().unwrap());
+                set(*is_zero_idx, F::zero());
+            }
+        }
+        WitnessBuilder::IndexedLogUpDenominator(
+            witness_idx,
+            sz_challenge,
+            WitnessCoefficient(index_coeff, index),
+            rs_challenge,
+            value,
+        ) => {
+            set(
+                *witness_idx,
+                get(*sz_challenge) - (*index_coeff * get(*index) + get(*rs_challenge) * get(*value)),
+            );
+        }
+        WitnessBuilder::MultiplicitiesForRange(start_idx, range_size, value_witnesses) => {
+            let mut multiplicities = vec![0u32; *range_size];
+            for value_witness_idx in value_witnesses {
+                let value = get(*value_witness_idx).truncate_to_u64();
+                multiplicities[value as usize] += 1;
+            }
+            for (i, count) in multiplicities.iter().enumerate() {
+                set(start_idx + i, F::from(*count));
+            }
+        }
+        WitnessBuilder::Challenge(_) => {
+            unreachable!("Challenge builders are solved sequentially outside a layer")
+        }
+        WitnessBuilder::LogUpDenominator(witness_idx, sz_challenge, WitnessCoefficient(value_coeff, value)) => {
+            set(*witness_idx, get(*sz_challenge) - (*value_coeff * get(*value)));
+        }
+        WitnessBuilder::ProductLinearOperation(
+            witness_idx,
+            ProductLinearTerm(x, a, b),
+            ProductLinearTerm(y, c, d),
+        ) => {
+            set(*witness_idx, (*a * get(*x) + *b) * (*c * get(*y) + *d));
+        }
+        WitnessBuilder::DigitalDecomposition(dd_struct) => {
+            // Assumed to exist on `DigitalDecompositionWitnessesSolver`, mirroring
+            // its `&mut`-based `solve` but writing through `SharedWitness`; see the
+            // module doc comment — `digits.rs` isn't part of this checkout.
+            dd_struct.solve_cells(witness);
+        }
+        WitnessBuilder::SpiceMultisetFactor(
+            witness_idx,
+            sz_challenge,
+            rs_challenge,
+            WitnessCoefficient(addr, addr_witness),
+            value,
+            WitnessCoefficient(timer, timer_witness),
+        ) => {
+            let rs = get(*rs_challenge);
+            set(
+                *witness_idx,
+                get(*sz_challenge)
+                    - (*addr * get(*addr_witness) + rs * get(*value) + rs * rs * *timer * get(*timer_witness)),
+            );
+        }
+        WitnessBuilder::SpiceWitnesses(spice_witnesses) => {
+            // Assumed to exist on `SpiceWitnessesSolver`, mirroring its `&mut`-based
+            // `solve` but writing through `SharedWitness`; see the module doc
+            // comment — `ram.rs` isn't part of this checkout.
+            spice_witnesses.solve_cells(witness);
+        }
+        WitnessBuilder::BinOpLookupDenominator(witness_idx, sz_challenge, rs_challenge, rs_challenge_sqrd, lhs, rhs, output) => {
+            let resolve = |v: &ConstantOrR1CSWitness<F>| match v {
+                ConstantOrR1CSWitness::Constant(c) => *c,
+                ConstantOrR1CSWitness::Witness(idx) => get(*idx),
+            };
+            set(
+                *witness_idx,
+                get(*sz_challenge)
+                    - (resolve(lhs) + get(*rs_challenge) * resolve(rhs) + get(*rs_challenge_sqrd) * resolve(output)),
+            );
+        }
+        WitnessBuilder::MultiplicitiesForBinOp(witness_idx, operands) => {
+            let resolve = |v: &ConstantOrR1CSWitness<F>| match v {
+                ConstantOrR1CSWitness::Constant(c) => *c,
+                ConstantOrR1CSWitness::Witness(idx) => get(*idx),
+            };
+            let mut multiplicities = vec![0u32; 2usize.pow(2 * BINOP_ATOMIC_BITS as u32)];
+            for (lhs, rhs) in operands {
+                let index = (resolve(lhs).truncate_to_u64() << BINOP_ATOMIC_BITS) + resolve(rhs).truncate_to_u64();
+                multiplicities[index as usize] += 1;
+            }
+            for (i, count) in multiplicities.iter().enumerate() {
+                set(witness_idx + i, F::from(*count));
+            }
+        }
+    }
+}
+
+/// Groups builders into layers where every read index a builder depends on
+/// was written by a builder in a strictly earlier layer (or has no producer
+/// among `builders`, e.g. it comes from the ACIR witness map or an earlier
+/// epoch).
+fn layer_by_dependency<'a, F: AcirField>(
+    builders: &'a [WitnessBuilder<F>],
+) -> Vec<Vec<&'a WitnessBuilder<F>>> {
+    let mut producer_layer: HashMap<usize, usize> = HashMap::new();
+    let mut read_without_producer: HashSet<usize> = HashSet::new();
+    let mut layer_of = Vec::with_capacity(builders.len());
+
+    for builder in builders {
+        let (reads, writes) = dependencies(builder);
+        let layer = reads
+            .iter()
+            .filter_map(|idx| producer_layer.get(idx))
+            .max()
+            .map_or(0, |&l| l + 1);
+        read_without_producer.extend(
+            reads.iter().copied().filter(|idx| !producer_layer.contains_key(idx)),
+        );
+        for idx in writes {
+            assert!(
+                !read_without_producer.contains(&idx),
+                "witness index {idx} is read before it is written in this epoch"
+            );
+            assert!(
+                producer_layer.insert(idx, layer).is_none(),
+                "witness index {idx} is written twice in this epoch"
+            );
+        }
+        layer_of.push(layer);
+    }
+
+    let num_layers = layer_of.iter().max().map_or(0, |&l| l + 1);
+    let mut layers = vec![Vec::new(); num_layers];
+    for (builder, layer) in builders.iter().zip(layer_of) {
+        layers[layer].push(builder);
+    }
+    layers
+}
+
+/// The witness indices a builder reads from (already-solved operands) and
+/// the witness indices it writes to.
+fn dependencies<F: AcirField>(builder: &WitnessBuilder<F>) -> (Vec<usize>, Vec<usize>) {
+    match builder {
+        WitnessBuilder::Constant(term) => (vec![], vec![term.0]),
+        WitnessBuilder::Acir(witness_idx, _) => (vec![], vec![*witness_idx]),
+        WitnessBuilder::Sum(witness_idx, operands) => (
+            operands.iter().map(|SumTerm(_, idx)| *idx).collect(),
+            vec![*witness_idx],
+        ),
+        WitnessBuilder::Product(witness_idx, a, b) => (vec![*a, *b], vec![*witness_idx]),
+        WitnessBuilder::Inverse(witness_idx, operand_idx) => {
+            (vec![*operand_idx], vec![*witness_idx])
+        }
+        WitnessBuilder::CheckedInverse(witness_idx, operand_idx, is_zero_idx) => {
+            (vec![*operand_idx], vec![*witness_idx, *is_zero_idx])
+        }
+        WitnessBuilder::IndexedLogUpDenominator(witness_idx, sz, coeff, rs, value) => (
+            vec![*sz, coeff.1, *rs, *value],
+            vec![*witness_idx],
+        ),
+        WitnessBuilder::MultiplicitiesForRange(start_idx, range_size, value_witnesses) => (
+            value_witnesses.clone(),
+            (*start_idx..*start_idx + *range_size).collect(),
+        ),
+        WitnessBuilder::Challenge(witness_idx) => (vec![], vec![*witness_idx]),
+        WitnessBuilder::LogUpDenominator(witness_idx, sz, coeff) => {
+            (vec![*sz, coeff.1], vec![*witness_idx])
+        }
+        WitnessBuilder::ProductLinearOperation(witness_idx, lhs, rhs) => {
+            (vec![lhs.0, rhs.0], vec![*witness_idx])
+        }
+        WitnessBuilder::DigitalDecomposition(dd_struct) => {
+            (dd_struct.reads(), dd_struct.writes())
+        }
+        WitnessBuilder::SpiceMultisetFactor(witness_idx, sz, rs, addr, value, timer) => (
+            vec![*sz, *rs, addr.1, *value, timer.1],
+            vec![*witness_idx],
+        ),
+        WitnessBuilder::SpiceWitnesses(spice_witnesses) => {
+            (spice_witnesses.reads(), spice_witnesses.writes())
+        }
+        WitnessBuilder::BinOpLookupDenominator(witness_idx, sz, rs, rs_sqrd, lhs, rhs, output) => {
+            let mut reads = vec![*sz, *rs, *rs_sqrd];
+            reads.extend(constant_or_witness_index(lhs));
+            reads.extend(constant_or_witness_index(rhs));
+            reads.extend(constant_or_witness_index(output));
+            (reads, vec![*witness_idx])
+        }
+        WitnessBuilder::MultiplicitiesForBinOp(witness_idx, operands) => {
+            let reads = operands
+                .iter()
+                .flat_map(|(lhs, rhs)| {
+                    constant_or_witness_index(lhs)
+                        .into_iter()
+                        .chain(constant_or_witness_index(rhs))
+                })
+                .collect();
+            let writes = (*witness_idx
+                ..*witness_idx + 2usize.pow(2 * BINOP_ATOMIC_BITS as u32))
+                .collect();
+            (reads, writes)
+        }
+    }
+}
+
+fn constant_or_witness_index<F>(value: &ConstantOrR1CSWitness<F>) -> Option<usize> {
+    match value {
+        ConstantOrR1CSWitness::Constant(_) => None,
+        ConstantOrR1CSWitness::Witness(idx) => Some(*idx),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sum_builder(witness_idx: usize, reads: &[usize]) -> WitnessBuilder<FieldElement> {
+        WitnessBuilder::Sum(
+            witness_idx,
+            reads.iter().map(|idx| SumTerm(None, *idx)).collect(),
+        )
+    }
+
+    #[test]
+    fn layer_by_dependency_chains_through_reads() {
+        // 0: Constant -> writes 0, layer 0 (no reads)
+        // 1: Constant -> writes 1, layer 0 (no reads)
+        // 2: Product(0, 1) -> reads 0 and 1, so layer 1
+        // 3: Sum(2) -> reads 2, so layer 2
+        let builders: Vec<WitnessBuilder<FieldElement>> = vec![
+            WitnessBuilder::Constant(ConstantTerm(0, FieldElement::from(1u32))),
+            WitnessBuilder::Constant(ConstantTerm(1, FieldElement::from(2u32))),
+            WitnessBuilder::Product(2, 0, 1),
+            sum_builder(3, &[2]),
+        ];
+
+        let layers = layer_by_dependency(&builders);
+
+        assert_eq!(layers.iter().map(Vec::len).collect::<Vec<_>>(), vec![2, 1, 1]);
+    }
+
+    #[test]
+    fn solve_into_shared_matches_sequential_program_order() {
+        // A transcript-free program (no Challenge), so it can be solved
+        // either strictly in program order or layer-by-layer/in parallel
+        // via solve_into_shared, and the two must agree.
+        let builders: Vec<WitnessBuilder<FieldElement>> = vec![
+            WitnessBuilder::Constant(ConstantTerm(0, FieldElement::from(3u32))),
+            WitnessBuilder::Constant(ConstantTerm(1, FieldElement::from(4u32))),
+            WitnessBuilder::Product(2, 0, 1),
+            sum_builder(3, &[0, 1, 2]),
+        ];
+        let acir_map = WitnessMap::from(std::collections::BTreeMap::new());
+
+        // "Serial" reference: run every builder through solve_into_shared, in
+        // program order, one at a time.
+        let mut serial = vec![None; 4];
+        let serial_shared = SharedWitness::new(&mut serial);
+        for builder in &builders {
+            solve_into_shared(builder, &acir_map, &serial_shared);
+        }
+
+        // Parallel: actually go through the dependency layering.
+        let mut parallel = vec![None; 4];
+        let parallel_shared = SharedWitness::new(&mut parallel);
+        for layer in layer_by_dependency(&builders) {
+            layer
+                .par_iter()
+                .for_each(|builder| solve_into_shared(builder, &acir_map, &parallel_shared));
+        }
+
+        assert_eq!(serial, parallel);
+        assert!(serial.iter().all(Option::is_some));
+    }
+}
@@ -0,0 +1,185 @@
+//! Sharded Spice RAM consistency.
+//!
+//! `WitnessBuilder::SpiceMultisetFactor` / `SpiceWitnesses` solve the Spice
+//! read/write memory-checking argument as one monolithic multiset product
+//! over every `(addr, value, timer)` tuple in the execution trace, which
+//! doesn't scale to programs with very large memory traces. `ShardedSpice`
+//! partitions that trace into fixed-size shards, has each shard compute its
+//! own local read-set and write-set product, and carries a running product
+//! across shards so the global
+//! `init_set * all_writes == all_reads * final_set`
+//! equality is reconstructed by multiplying the per-shard partial products
+//! together. `sz_challenge`/`rs_challenge` are drawn once, via
+//! `WitnessBuilder::Challenge`, and shared by every shard.
+//!
+//! Status: this module is the sharded product math only. `WitnessBuilder`,
+//! `SpiceMultisetFactor` and `SpiceWitnesses` are defined in
+//! `provekit_common`, which isn't part of this checkout, so adding the
+//! `WitnessBuilder` variant (plus the shard-index R1CS column and the
+//! `init_set`/`final_set` wiring) that would make a Noir program actually
+//! select this path has to land alongside that crate, not here. Until then
+//! `witness_builder.rs`'s `SpiceMultisetFactor`/`SpiceWitnesses` arms keep
+//! computing the original, unsharded product, and no Noir program exercises
+//! `ShardedSpice` at all. Merging this module is merging a tracked stub, not
+//! shipping the sharded argument — it isn't "done" until it's wired in.
+
+use {super::witness_builder::AcirField, ark_std::One};
+
+/// One timestamped memory operation in the `(addr, value, timer)` encoding
+/// used by `SpiceMultisetFactor`.
+#[derive(Clone, Copy)]
+pub struct SpiceOp<F> {
+    pub addr:  F,
+    pub value: F,
+    pub timer: F,
+}
+
+/// Sharding configuration for the Spice argument: how many operations each
+/// shard covers, trading off memory (smaller shards) against parallelism
+/// overhead (more shards).
+pub struct ShardedSpice {
+    shard_size: usize,
+}
+
+impl ShardedSpice {
+    pub fn new(shard_size: usize) -> Self {
+        assert!(shard_size > 0, "shard_size must be positive");
+        Self { shard_size }
+    }
+
+    pub fn num_shards(&self, num_ops: usize) -> usize {
+        num_ops.div_ceil(self.shard_size)
+    }
+
+    fn shard_bounds(&self, shard_index: usize, num_ops: usize) -> (usize, usize) {
+        let start = shard_index * self.shard_size;
+        let end = ((shard_index + 1) * self.shard_size).min(num_ops);
+        (start, end)
+    }
+
+    /// The Spice per-operation factor `sz - (addr + rs*value + rs^2*timer)`,
+    /// matching `WitnessBuilder::SpiceMultisetFactor`'s encoding (with an
+    /// implicit address coefficient of 1, since shards don't change how an
+    /// individual op is encoded).
+    fn factor<F: AcirField>(op: SpiceOp<F>, sz_challenge: F, rs_challenge: F) -> F {
+        sz_challenge - (op.addr + rs_challenge * op.value + rs_challenge * rs_challenge * op.timer)
+    }
+
+    /// Computes, for each shard, the local product of `factor(op)` over that
+    /// shard's operations, and the running (carried) product up to and
+    /// including that shard. `carries[i]` is the product over shards
+    /// `0..=i`, so `carries[num_shards - 1]` is the global product over all
+    /// operations — i.e. what the unsharded argument would have computed
+    /// directly.
+    pub fn shard_products<F: AcirField>(
+        &self,
+        ops: &[SpiceOp<F>],
+        sz_challenge: F,
+        rs_challenge: F,
+    ) -> (Vec<F>, Vec<F>) {
+        let num_shards = self.num_shards(ops.len());
+        let mut locals = Vec::with_capacity(num_shards);
+        let mut carries = Vec::with_capacity(num_shards);
+        let mut running = F::one();
+        for shard_index in 0..num_shards {
+            let (start, end) = self.shard_bounds(shard_index, ops.len());
+            let local = ops[start..end]
+                .iter()
+                .fold(F::one(), |acc, op| acc * Self::factor(*op, sz_challenge, rs_challenge));
+            running *= local;
+            locals.push(local);
+            carries.push(running);
+        }
+        (locals, carries)
+    }
+
+    /// Solves the local-product and carry witnesses for both the read-set
+    /// and write-set of a Spice argument, writing:
+    /// - `witness[local_start + shard]` / `witness[local_start + num_shards +
+    ///   shard]` for reads, and the same offset by `2 * num_shards` for writes.
+    ///
+    /// The caller is expected to have already reserved `4 * num_shards`
+    /// contiguous witness indices starting at `local_start` (local-reads,
+    /// carry-reads, local-writes, carry-writes, each `num_shards` wide).
+    pub fn solve<F: AcirField>(
+        &self,
+        reads: &[SpiceOp<F>],
+        writes: &[SpiceOp<F>],
+        sz_challenge: F,
+        rs_challenge: F,
+        local_start: usize,
+        witness: &mut [Option<F>],
+    ) {
+        let num_shards = self.num_shards(reads.len().max(writes.len()));
+        let (read_locals, read_carries) = self.shard_products(reads, sz_challenge, rs_challenge);
+        let (write_locals, write_carries) = self.shard_products(writes, sz_challenge, rs_challenge);
+
+        for shard in 0..num_shards {
+            witness[local_start + shard] = read_locals.get(shard).copied();
+            witness[local_start + num_shards + shard] = read_carries.get(shard).copied();
+            witness[local_start + 2 * num_shards + shard] = write_locals.get(shard).copied();
+            witness[local_start + 3 * num_shards + shard] = write_carries.get(shard).copied();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, provekit_common::FieldElement};
+
+    fn op(addr: u64, value: u64, timer: u64) -> SpiceOp<FieldElement> {
+        SpiceOp {
+            addr:  FieldElement::from(addr),
+            value: FieldElement::from(value),
+            timer: FieldElement::from(timer),
+        }
+    }
+
+    #[test]
+    fn last_carry_matches_the_unsharded_product() {
+        let ops = vec![op(0, 1, 0), op(1, 2, 1), op(2, 3, 2), op(0, 4, 3), op(1, 5, 4)];
+        let sz = FieldElement::from(7u32);
+        let rs = FieldElement::from(3u32);
+
+        let unsharded = ops
+            .iter()
+            .fold(FieldElement::one(), |acc, op| acc * ShardedSpice::factor(*op, sz, rs));
+
+        for shard_size in [1, 2, 3, 5, 8] {
+            let sharded = ShardedSpice::new(shard_size);
+            let (_, carries) = sharded.shard_products(&ops, sz, rs);
+            assert_eq!(*carries.last().unwrap(), unsharded, "shard_size = {shard_size}");
+        }
+    }
+
+    #[test]
+    fn num_shards_rounds_up() {
+        let sharded = ShardedSpice::new(4);
+        assert_eq!(sharded.num_shards(0), 0);
+        assert_eq!(sharded.num_shards(4), 1);
+        assert_eq!(sharded.num_shards(5), 2);
+        assert_eq!(sharded.num_shards(8), 2);
+    }
+
+    #[test]
+    fn solve_writes_locals_and_carries_for_reads_and_writes() {
+        let reads = vec![op(0, 1, 0), op(1, 2, 1), op(2, 3, 2)];
+        let writes = vec![op(0, 9, 0)];
+        let sz = FieldElement::from(11u32);
+        let rs = FieldElement::from(5u32);
+        let sharded = ShardedSpice::new(2);
+        let num_shards = sharded.num_shards(reads.len().max(writes.len()));
+
+        let mut witness = vec![None; 4 * num_shards];
+        sharded.solve(&reads, &writes, sz, rs, 0, &mut witness);
+
+        let (read_locals, read_carries) = sharded.shard_products(&reads, sz, rs);
+        let (write_locals, write_carries) = sharded.shard_products(&writes, sz, rs);
+        for shard in 0..num_shards {
+            assert_eq!(witness[shard], read_locals.get(shard).copied());
+            assert_eq!(witness[num_shards + shard], read_carries.get(shard).copied());
+            assert_eq!(witness[2 * num_shards + shard], write_locals.get(shard).copied());
+            assert_eq!(witness[3 * num_shards + shard], write_carries.get(shard).copied());
+        }
+    }
+}
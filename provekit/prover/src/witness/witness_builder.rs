@@ -1,8 +1,8 @@
 use {
     crate::witness::{digits::DigitalDecompositionWitnessesSolver, ram::SpiceWitnessesSolver},
     acir::native_types::WitnessMap,
-    ark_ff::{Field, PrimeField},
-    ark_std::Zero,
+    ark_ff::{BigInteger, Field, PrimeField},
+    ark_std::{One, Zero},
     provekit_common::{
         skyscraper::SkyscraperSponge,
         utils::noir_to_native,
@@ -12,24 +12,53 @@ use {
         },
         FieldElement, NoirElement,
     },
-    spongefish::{codecs::arkworks_algebra::UnitToField, ProverState},
+    spongefish::{codecs::arkworks_algebra::UnitToField, DuplexSpongeInterface, ProverState},
+    std::ops::{Add, Mul, Sub},
 };
 
-pub trait WitnessBuilderSolver {
+/// The field operations the witness solver needs, factored out of the
+/// concrete BN254 `FieldElement` so the solver can be retargeted at other
+/// curves/fields without forking this module.
+///
+/// Mirrors ACVM's own move to being generic over the field: everything here
+/// is either a `PrimeField` supertrait method or a small addition
+/// (`truncate_to_u64`, the Fiat-Shamir sponge) that the solver happens to
+/// rely on.
+pub trait AcirField:
+    PrimeField + From<u32> + Add<Output = Self> + Mul<Output = Self> + Sub<Output = Self>
+{
+    /// The sponge used to draw Fiat-Shamir challenges over this field.
+    type Sponge: DuplexSpongeInterface<u8>;
+
+    /// The least-significant 64 bits of this element's canonical representation.
+    ///
+    /// Used where a witness is known to fit in a `u64` (range/bin-op lookup
+    /// indices), mirroring the `into_bigint().0[0]` pattern previously
+    /// hardcoded against BN254's `FieldElement`.
+    fn truncate_to_u64(&self) -> u64 {
+        self.into_bigint().as_ref()[0]
+    }
+}
+
+impl AcirField for FieldElement {
+    type Sponge = SkyscraperSponge;
+}
+
+pub trait WitnessBuilderSolver<F: AcirField> {
     fn solve(
         &self,
         acir_witness_idx_to_value_map: &WitnessMap<NoirElement>,
-        witness: &mut [Option<FieldElement>],
-        transcript: &mut ProverState<SkyscraperSponge, FieldElement>,
+        witness: &mut [Option<F>],
+        transcript: &mut ProverState<F::Sponge, F>,
     );
 }
 
-impl WitnessBuilderSolver for WitnessBuilder {
+impl<F: AcirField> WitnessBuilderSolver<F> for WitnessBuilder<F> {
     fn solve(
         &self,
         acir_witness_idx_to_value_map: &WitnessMap<NoirElement>,
-        witness: &mut [Option<FieldElement>],
-        transcript: &mut ProverState<SkyscraperSponge, FieldElement>,
+        witness: &mut [Option<F>],
+        transcript: &mut ProverState<F::Sponge, F>,
     ) {
         match self {
             WitnessBuilder::Constant(ConstantTerm(witness_idx, c)) => {
@@ -53,18 +82,40 @@ impl WitnessBuilderSolver for WitnessBuilder {
                                 witness[*witness_idx].unwrap()
                             }
                         })
-                        .fold(FieldElement::zero(), |acc, x| acc + x),
+                        .fold(F::zero(), |acc, x| acc + x),
                 );
             }
             WitnessBuilder::Product(witness_idx, operand_idx_a, operand_idx_b) => {
-                let a: FieldElement = witness[*operand_idx_a].unwrap();
-                let b: FieldElement = witness[*operand_idx_b].unwrap();
+                let a: F = witness[*operand_idx_a].unwrap();
+                let b: F = witness[*operand_idx_b].unwrap();
                 witness[*witness_idx] = Some(a * b);
             }
             WitnessBuilder::Inverse(witness_idx, operand_idx) => {
-                let operand: FieldElement = witness[*operand_idx].unwrap();
+                let operand: F = witness[*operand_idx].unwrap();
                 witness[*witness_idx] = Some(operand.inverse().unwrap());
             }
+            WitnessBuilder::CheckedInverse(witness_idx, operand_idx, is_zero_idx) => {
+                // Mirrors Noir/Brillig semantics: division by zero yields an inverse of 0
+                // rather than panicking, with `is_zero` flagging the degenerate case so the
+                // R1CS guard constraints can still reject the proof.
+                //
+                // UNSOUND ON ITS OWN: this arm only changes what the *solver* computes.
+                // Nothing here ties `is_zero`/the inverse to `operand` in the constraint
+                // system — the matching guard constraints (`operand * is_zero == 0`,
+                // `operand * inverse == 1 - is_zero`) live in the r1cs-compiler crate,
+                // not part of this checkout, and have not landed. Do not wire
+                // `CheckedInverse` into a live proving path until those constraints exist:
+                // until then, a malicious prover can set `is_zero`/`inverse` to anything
+                // for a zero operand and the proof still verifies.
+                let operand: F = witness[*operand_idx].unwrap();
+                if operand.is_zero() {
+                    witness[*witness_idx] = Some(F::zero());
+                    witness[*is_zero_idx] = Some(F::one());
+                } else {
+                    witness[*witness_idx] = Some(operand.inverse().unwrap());
+                    witness[*is_zero_idx] = Some(F::zero());
+                }
+            }
             WitnessBuilder::IndexedLogUpDenominator(
                 witness_idx,
                 sz_challenge,
@@ -84,15 +135,15 @@ impl WitnessBuilderSolver for WitnessBuilder {
                 for value_witness_idx in value_witnesses {
                     // If the value is representable as just a u64, then it should be the least
                     // significant value in the BigInt representation.
-                    let value = witness[*value_witness_idx].unwrap().into_bigint().0[0];
+                    let value = witness[*value_witness_idx].unwrap().truncate_to_u64();
                     multiplicities[value as usize] += 1;
                 }
                 for (i, count) in multiplicities.iter().enumerate() {
-                    witness[start_idx + i] = Some(FieldElement::from(*count));
+                    witness[start_idx + i] = Some(F::from(*count));
                 }
             }
             WitnessBuilder::Challenge(witness_idx) => {
-                let mut one = [FieldElement::zero(); 1];
+                let mut one = [F::zero(); 1];
                 let _ = transcript.fill_challenge_scalars(&mut one);
                 witness[*witness_idx] = Some(one[0]);
             }
@@ -180,12 +231,11 @@ impl WitnessBuilderSolver for WitnessBuilder {
                             witness[*witness_idx].unwrap()
                         }
                     };
-                    let index =
-                        (lhs.into_bigint().0[0] << BINOP_ATOMIC_BITS) + rhs.into_bigint().0[0];
+                    let index = (lhs.truncate_to_u64() << BINOP_ATOMIC_BITS) + rhs.truncate_to_u64();
                     multiplicities[index as usize] += 1;
                 }
                 for (i, count) in multiplicities.iter().enumerate() {
-                    witness[witness_idx + i] = Some(FieldElement::from(*count));
+                    witness[witness_idx + i] = Some(F::from(*count));
                 }
             }
         }
@@ -4,7 +4,7 @@ use {
     nargo_cli::cli::compile_cmd::compile_workspace_full,
     nargo_toml::{resolve_workspace_from_toml, PackageSelection},
     noirc_driver::CompileOptions,
-    provekit_common::NoirProofScheme,
+    provekit_common::{NoirProofScheme, Proof},
     provekit_prover::NoirProofSchemeProver,
     provekit_r1cs_compiler::NoirProofSchemeBuilder,
     provekit_verifier::NoirProofSchemeVerifier,
@@ -24,6 +24,14 @@ struct NargoTomlPackage {
 }
 
 fn test_compiler(test_case_path: impl AsRef<Path>) {
+    let (proof_schema, proof) = prove_case(test_case_path);
+
+    proof_schema.verify(&proof).expect("Verifying proof");
+}
+
+/// Compiles and proves a single Noir test case, returning the scheme and
+/// proof instead of verifying them, so callers can combine several of them.
+fn prove_case(test_case_path: impl AsRef<Path>) -> (NoirProofScheme, Proof) {
     let test_case_path = test_case_path.as_ref();
 
     compile_workspace(test_case_path).expect("Compiling workspace");
@@ -47,7 +55,7 @@ fn test_compiler(test_case_path: impl AsRef<Path>) {
         .prove(&input_map)
         .expect("While proving Noir program statement");
 
-    proof_schema.verify(&proof).expect("Verifying proof");
+    (proof_schema, proof)
 }
 
 pub fn compile_workspace(workspace_path: impl AsRef<Path>) -> Result<Workspace> {
@@ -85,3 +93,10 @@ pub fn compile_workspace(workspace_path: impl AsRef<Path>) -> Result<Workspace>
 fn case(path: &str) {
     test_compiler(path);
 }
+
+// TODO(aggregation): proof aggregation (`NoirProofScheme::aggregate` /
+// `verify_aggregate`) is not implemented anywhere in this checkout — it's a
+// cross-cutting feature spanning the prover, verifier, and transcript code,
+// not something `prove_case` alone can exercise. Tracked as follow-up work;
+// `prove_case` is kept (above) so the aggregation test can be added here
+// once that API exists, instead of landing a test that can't compile.